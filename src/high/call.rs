@@ -0,0 +1,204 @@
+//! Typed, safe-ish dynamic call assembly.
+//!
+//! This is the `high`-layer counterpart to the closure facility above:
+//! where `ClosureN` turns a Rust closure into a C function pointer,
+//! [`CallBuilder`](struct.CallBuilder.html) goes the other way, turning
+//! a raw C code pointer whose signature is only known at runtime into
+//! something that can be called without hand-rolling a
+//! [`middle::Cif`](../../middle/struct.Cif.html).
+
+use crate::low::CodePtr;
+use crate::middle::{self, Arg, Type};
+
+use super::types::CType;
+
+/// Accumulates a call’s argument types and values before it is
+/// [`call`](#method.call)ed.
+///
+/// ```
+/// use std::os::raw::c_void;
+/// use libffi::high::CallBuilder;
+///
+/// unsafe extern "C" fn add(a: i32, b: i32) -> i32 {
+///     a + b
+/// }
+///
+/// let result: i32 = unsafe {
+///     CallBuilder::new()
+///         .arg(&1i32)
+///         .arg(&2i32)
+///         .call(add as *const c_void)
+/// };
+/// assert_eq!(3, result);
+/// ```
+///
+/// Variadic calls (e.g. to `printf`) push their fixed arguments with
+/// [`arg`](#method.arg) and their call-specific trailing arguments with
+/// [`var_arg`](#method.var_arg); each distinct combination of variadic
+/// argument types builds its own CIF under the hood, just as the C
+/// library requires.
+pub struct CallBuilder<'a> {
+    arg_types: Vec<Type>,
+    // Keeps the `Arg`s alive for the duration of the builder; they're
+    // only raw pointers into the caller's arguments otherwise.
+    args: Vec<Arg>,
+    // Set to the argument count as of the first `var_arg` call.
+    nfixedargs: Option<usize>,
+    _marker: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Default for CallBuilder<'a> {
+    fn default() -> Self {
+        CallBuilder {
+            arg_types: Vec::new(),
+            args: Vec::new(),
+            nfixedargs: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a> CallBuilder<'a> {
+    /// Creates a new, empty `CallBuilder`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Pushes a fixed argument of a type understood by libffi
+    /// (see [`CType`](trait.CType.html)).
+    pub fn arg<T: CType>(mut self, value: &'a T) -> Self {
+        self.arg_types.push(T::reify());
+        self.args.push(Arg::new(value));
+        self
+    }
+
+    /// Pushes an argument whose libffi type isn’t known statically via
+    /// [`CType`](trait.CType.html) — notably a `struct` built with
+    /// [`Type::structure`](../../middle/struct.Type.html#method.structure).
+    pub fn arg_typed<T>(mut self, value: &'a T, ty: Type) -> Self {
+        self.arg_types.push(ty);
+        self.args.push(Arg::new(value));
+        self
+    }
+
+    /// Pushes a variadic argument for this particular call.
+    ///
+    /// The first call to `var_arg` marks every argument pushed so far
+    /// as fixed; all of them, plus every subsequent `var_arg`, must
+    /// exactly match the callee’s variadic arguments for this call.
+    pub fn var_arg<T: CType>(mut self, value: &'a T) -> Self {
+        if self.nfixedargs.is_none() {
+            self.nfixedargs = Some(self.args.len());
+        }
+        self.arg_types.push(T::reify());
+        self.args.push(Arg::new(value));
+        self
+    }
+
+    /// Builds the CIF for the accumulated argument types and `R` as the
+    /// result type, without calling anything; useful when the same
+    /// signature will be invoked more than once.
+    pub fn into_cif<R: CType>(self) -> (middle::Cif, Vec<Arg>) {
+        self.into_cif_typed(R::reify())
+    }
+
+    /// Builds the CIF for the accumulated argument types and an
+    /// explicitly supplied result type; the [`call_typed`](#method.call_typed)
+    /// counterpart to [`into_cif`](#method.into_cif).
+    fn into_cif_typed(self, result: Type) -> (middle::Cif, Vec<Arg>) {
+        let cif = match self.nfixedargs {
+            None => middle::Cif::new(self.arg_types, result),
+            Some(nfixedargs) => {
+                let mut arg_types = self.arg_types;
+                let var_types = arg_types.split_off(nfixedargs);
+                middle::Cif::new_variadic(arg_types, var_types, result)
+            }
+        };
+        (cif, self.args)
+    }
+
+    /// Invokes `fun` with the accumulated arguments, interpreting its
+    /// return value as `R`.
+    ///
+    /// # Safety
+    ///
+    /// `fun` must actually be callable with the arguments pushed via
+    /// [`arg`](#method.arg) and [`var_arg`](#method.var_arg), using the
+    /// platform’s default C calling convention, and must return a value
+    /// of type `R`.
+    pub unsafe fn call<R: CType>(self, fun: *const std::os::raw::c_void) -> R {
+        let (cif, args) = self.into_cif::<R>();
+        cif.call(CodePtr::from_ptr(fun), &args)
+    }
+
+    /// Invokes `fun` as in [`call`](#method.call), but with an
+    /// explicitly supplied result type instead of `R::reify()` — for
+    /// result types (notably structs) that don’t implement
+    /// [`CType`](trait.CType.html).
+    ///
+    /// # Safety
+    ///
+    /// Same caveats as [`call`](#method.call), plus `result` must
+    /// accurately describe the layout of `R`.
+    pub unsafe fn call_typed<R>(self, fun: *const std::os::raw::c_void, result: Type) -> R {
+        let (cif, args) = self.into_cif_typed(result);
+        cif.call(CodePtr::from_ptr(fun), &args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[test]
+    fn call_with_two_fixed_args() {
+        let result: i32 = unsafe {
+            CallBuilder::new()
+                .arg(&1i32)
+                .arg(&2i32)
+                .call(add as *const std::os::raw::c_void)
+        };
+        assert_eq!(3, result);
+    }
+
+    #[test]
+    fn call_with_one_fixed_and_one_variadic_arg() {
+        let result: i32 = unsafe {
+            CallBuilder::new()
+                .arg(&1i32)
+                .var_arg(&2i32)
+                .call(add as *const std::os::raw::c_void)
+        };
+        assert_eq!(3, result);
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    unsafe extern "C" fn make_point(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn call_typed_with_struct_result() {
+        let point_type = crate::middle::Type::structure(vec![
+            crate::middle::Type::i32(),
+            crate::middle::Type::i32(),
+        ]);
+        let result: Point = unsafe {
+            CallBuilder::new()
+                .arg(&1i32)
+                .arg(&2i32)
+                .call_typed(make_point as *const std::os::raw::c_void, point_type)
+        };
+        assert_eq!(Point { x: 1, y: 2 }, result);
+    }
+}
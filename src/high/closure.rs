@@ -0,0 +1,110 @@
+//! Turns Rust closures into ordinary C function pointers.
+
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+use crate::low::CodePtr;
+use crate::middle;
+use crate::raw;
+
+use super::types::CType;
+
+macro_rules! define_closure {
+    (
+        $closure:ident,
+        $fn_ptr:ident,
+        ( $($arg:ident : $arg_ty:ident),* )
+    ) => {
+        /// A C function pointer backed by a Rust closure capturing
+        #[doc = stringify!($($arg_ty),*)]
+        /// arguments.
+        pub struct $closure<'a, $($arg_ty,)* R> {
+            // Boxed so its address is stable: `closure` below borrows
+            // from it, and the two must move around together.
+            _cif: Box<middle::Cif>,
+            closure: middle::Closure<'static>,
+            _marker: PhantomData<&'a (dyn Fn($($arg_ty,)*) -> R + 'a)>,
+        }
+
+        /// A bare C function pointer matching the signature of the
+        #[doc = stringify!($closure)]
+        /// it was obtained from.
+        pub type $fn_ptr<$($arg_ty,)* R> = unsafe extern "C" fn($($arg_ty,)*) -> R;
+
+        impl<'a, $($arg_ty: CType,)* R: CType> $closure<'a, $($arg_ty,)* R> {
+            /// Wraps a Rust closure in a C function pointer of matching
+            /// signature. The closure must be borrowed for the
+            /// lifetime `'a` of the resulting
+            #[doc = stringify!($closure)]
+            /// .
+            pub fn new<F>(f: &'a F) -> Self
+            where
+                F: Fn($($arg_ty,)*) -> R,
+            {
+                unsafe extern "C" fn callback<F, $($arg_ty: CType,)* R: CType>(
+                    _cif: *mut raw::ffi_cif,
+                    result: *mut c_void,
+                    args: *mut *mut c_void,
+                    userdata: *mut c_void,
+                ) where
+                    F: Fn($($arg_ty,)*) -> R,
+                {
+                    #[allow(unused_mut, unused_variables)]
+                    let mut i = 0;
+                    $(
+                        let $arg: $arg_ty = *(*args.add(i) as *const $arg_ty);
+                        i += 1;
+                    )*
+                    let _ = i;
+                    let f = &*(userdata as *const F);
+                    *(result as *mut R) = f($($arg,)*);
+                }
+
+                let cif = Box::new(middle::Cif::new(
+                    vec![$($arg_ty::reify(),)*],
+                    R::reify(),
+                ));
+
+                // `cif` is heap-allocated and never moved or mutated
+                // again, so lending this reference out for `'static`
+                // is sound: it stays valid for as long as `cif` does,
+                // i.e. for the lifetime of the `$closure` below, which
+                // owns both fields together.
+                let cif_ref: &'static middle::Cif =
+                    unsafe { &*(cif.as_ref() as *const middle::Cif) };
+
+                let closure = unsafe {
+                    middle::Closure::new(
+                        cif_ref,
+                        callback::<F, $($arg_ty,)* R>,
+                        f as *const F as *const c_void,
+                    )
+                };
+
+                $closure {
+                    _cif: cif,
+                    closure,
+                    _marker: PhantomData,
+                }
+            }
+
+            /// Gets the C code pointer, usable as an ordinary
+            #[doc = stringify!($fn_ptr)]
+            /// .
+            pub fn code_ptr(&self) -> $fn_ptr<$($arg_ty,)* R> {
+                unsafe {
+                    std::mem::transmute_copy::<CodePtr, $fn_ptr<$($arg_ty,)* R>>(
+                        self.closure.code_ptr(),
+                    )
+                }
+            }
+        }
+    };
+}
+
+define_closure!(Closure0, FnPtr0, ());
+define_closure!(Closure1, FnPtr1, (a0: A0));
+define_closure!(Closure2, FnPtr2, (a0: A0, a1: A1));
+define_closure!(Closure3, FnPtr3, (a0: A0, a1: A1, a2: A2));
+define_closure!(Closure4, FnPtr4, (a0: A0, a1: A1, a2: A2, a3: A3));
+define_closure!(Closure5, FnPtr5, (a0: A0, a1: A1, a2: A2, a3: A3, a4: A4));
@@ -0,0 +1,27 @@
+//! Thin `high`-layer wrapper around
+//! [`middle::dynamic`](../../middle/dynamic/index.html)’s runtime-typed
+//! invocation interface, for scripting-language FFIs that only learn a
+//! callee’s signature at runtime.
+
+use crate::low::CodePtr;
+use crate::middle::dynamic::{self, CallError, Signature, Value};
+
+pub use crate::middle::dynamic::{CallError as DynamicCallError, Signature as DynamicSignature};
+pub use crate::middle::dynamic::{Tag as DynamicTag, Value as DynamicValue};
+
+/// Calls `fun` according to a runtime-constructed `signature`, checking
+/// `args` against it before dispatching. See
+/// [`middle::dynamic::call`](../../middle/dynamic/fn.call.html) for the
+/// full contract.
+///
+/// # Safety
+///
+/// `fun` must actually be callable according to `signature`, using the
+/// platform’s default C calling convention.
+pub unsafe fn call(
+    fun: *const std::os::raw::c_void,
+    signature: &Signature,
+    args: &[Value],
+) -> Result<Value, CallError> {
+    dynamic::call(CodePtr::from_ptr(fun), signature, args)
+}
@@ -0,0 +1,18 @@
+//! High layer providing automatic marshalling of Rust closures into C
+//! function pointers.
+//!
+//! This layer sits on top of [`middle`](../middle/index.html), hiding
+//! its CIF- and memory-management details behind ordinary, typed Rust
+//! APIs.
+
+mod types;
+pub use self::types::CType;
+
+mod closure;
+pub use self::closure::{Closure0, Closure1, Closure2, Closure3, Closure4, Closure5};
+pub use self::closure::{FnPtr0, FnPtr1, FnPtr2, FnPtr3, FnPtr4, FnPtr5};
+
+mod call;
+pub use self::call::CallBuilder;
+
+pub mod dynamic;
@@ -0,0 +1,48 @@
+//! Maps Rust types onto their C equivalents.
+
+use crate::middle::Type;
+
+/// Types that can cross the FFI boundary as scalar arguments or return
+/// values in the `high` layer.
+///
+/// This trait is sealed in spirit (if not in the type system) — it’s
+/// only meant to be implemented for the primitive types libffi itself
+/// understands.
+pub trait CType: Copy {
+    /// Returns the libffi [`middle::Type`](../../middle/struct.Type.html)
+    /// corresponding to `Self`.
+    fn reify() -> Type;
+}
+
+macro_rules! impl_ctype {
+    ($rust_type:ty, $ctor:ident) => {
+        impl CType for $rust_type {
+            fn reify() -> Type {
+                Type::$ctor()
+            }
+        }
+    };
+}
+
+impl_ctype!(u8, u8);
+impl_ctype!(i8, i8);
+impl_ctype!(u16, u16);
+impl_ctype!(i16, i16);
+impl_ctype!(u32, u32);
+impl_ctype!(i32, i32);
+impl_ctype!(u64, u64);
+impl_ctype!(i64, i64);
+impl_ctype!(f32, f32);
+impl_ctype!(f64, f64);
+
+impl<T> CType for *const T {
+    fn reify() -> Type {
+        Type::pointer()
+    }
+}
+
+impl<T> CType for *mut T {
+    fn reify() -> Type {
+        Type::pointer()
+    }
+}
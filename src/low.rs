@@ -0,0 +1,156 @@
+//! Low-level wrapping of libffi, this layer is a little more type-safe
+//! than the raw bindings in [`raw`](../raw/index.html), but is otherwise
+//! close to a one-to-one mapping to the underlying C library.
+//!
+//! This module is intended for users who need as much control as
+//! possible over how calls get dispatched — the
+//! [`middle`](../middle/index.html) layer adds memory management and a
+//! little type checking on top of this, while remaining unsafe.
+
+use std::mem;
+use std::os::raw::c_void;
+
+use crate::raw;
+
+pub use crate::raw::{ffi_abi, ffi_abi_FFI_DEFAULT_ABI as FFI_DEFAULT_ABI, ffi_cif, ffi_type};
+
+/// The statically-allocated primitive type representations supplied by
+/// the underlying C libffi.
+pub mod types {
+    pub use crate::raw::{
+        ffi_type_double as double, ffi_type_float as float, ffi_type_pointer as pointer,
+        ffi_type_sint8 as sint8, ffi_type_sint16 as sint16, ffi_type_sint32 as sint32,
+        ffi_type_sint64 as sint64, ffi_type_uint8 as uint8, ffi_type_uint16 as uint16,
+        ffi_type_uint32 as uint32, ffi_type_uint64 as uint64, ffi_type_void as void,
+    };
+}
+
+/// Wraps a C code pointer, the sort of thing you normally get back from
+/// `dlsym`, for use in [`call`](fn.call.html) and friends.
+///
+/// `CodePtr` can also point to the code of a Rust function, in which
+/// case it’s assumed to use the platform’s C calling convention.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CodePtr(*mut c_void);
+
+impl CodePtr {
+    /// Initializes a code pointer from a function pointer.
+    pub fn from_fun(fun: unsafe extern "C" fn()) -> Self {
+        CodePtr(fun as *mut c_void)
+    }
+
+    /// Initializes a code pointer from a void pointer.
+    pub fn from_ptr(fun: *const c_void) -> Self {
+        CodePtr(fun as *mut c_void)
+    }
+
+    /// Gets the code pointer as a `const` pointer to `c_void`.
+    pub fn as_ptr(&self) -> *const c_void {
+        self.0 as *const c_void
+    }
+
+    /// Gets the code pointer as a mutable pointer to `c_void`.
+    pub fn as_mut_ptr(&self) -> *mut c_void {
+        self.0
+    }
+
+    /// Gets the code pointer as a C function pointer.
+    pub fn as_fun(&self) -> &unsafe extern "C" fn() {
+        unsafe { mem::transmute(&self.0) }
+    }
+}
+
+/// Initalizes a CIF (Call InterFace) with the given ABI, argument types,
+/// and return type.
+///
+/// We need to call this function to initialize a CIF before we can use
+/// it to call a function or create a closure.
+///
+/// # Safety
+///
+/// The resulting CIF retains copies of the pointers `atypes` and
+/// `rtype`, so if the caller frees the memory behind either of these
+/// pointers before the CIF is done being used, undefined behavior
+/// results.
+pub unsafe fn prep_cif(
+    cif: *mut ffi_cif,
+    abi: ffi_abi,
+    nargs: usize,
+    rtype: *mut ffi_type,
+    atypes: *mut *mut ffi_type,
+) -> Result<(), raw::ffi_status> {
+    let status = raw::ffi_prep_cif(cif, abi, nargs as u32, rtype, atypes);
+    status_to_result(status, ())
+}
+
+/// Initializes a CIF for a variadic function, as with `ffi_prep_cif_var`.
+///
+/// `nfixedargs` is the number of fixed (non-variadic) arguments,
+/// while `ntotalargs` is the total number of arguments, including the
+/// concrete types of whatever variadic arguments are being passed for
+/// *this particular call*. Each distinct combination of trailing
+/// variadic types needs its own CIF, just as in the C library.
+///
+/// # Safety
+///
+/// Same caveats as [`prep_cif`](fn.prep_cif.html).
+pub unsafe fn prep_cif_var(
+    cif: *mut ffi_cif,
+    abi: ffi_abi,
+    nfixedargs: usize,
+    ntotalargs: usize,
+    rtype: *mut ffi_type,
+    atypes: *mut *mut ffi_type,
+) -> Result<(), raw::ffi_status> {
+    let status = raw::ffi_prep_cif_var(
+        cif,
+        abi,
+        nfixedargs as u32,
+        ntotalargs as u32,
+        rtype,
+        atypes,
+    );
+    status_to_result(status, ())
+}
+
+fn status_to_result<R>(status: raw::ffi_status, good: R) -> Result<R, raw::ffi_status> {
+    if status == raw::ffi_status_FFI_OK {
+        Ok(good)
+    } else {
+        Err(status)
+    }
+}
+
+/// Calls a function through the given CIF, with the given arguments and
+/// pointer to storage for the result.
+///
+/// # Safety
+///
+/// The CIF must have been properly initialized with
+/// [`prep_cif`](fn.prep_cif.html) or
+/// [`prep_cif_var`](fn.prep_cif_var.html), and the number and types of
+/// the arguments must match what it was prepared with; `rvalue` must
+/// point at a properly aligned buffer large enough for the return type.
+pub unsafe fn call<R>(cif: *mut ffi_cif, fun: CodePtr, args: *mut *mut c_void) -> R {
+    // libffi documents that `rvalue` must be large enough to hold a
+    // general register on the target machine: integer return types
+    // smaller than `ffi_arg` (e.g. `sint8`) are widened to register size
+    // when `ffi_call` writes them back. Sizing the buffer for `R` alone
+    // would make that write out-of-bounds for small-int/`f32` returns,
+    // so the storage is unioned with an `ffi_arg` to guarantee it's at
+    // least register-sized; `R`'s value still lands at the buffer's base
+    // address, so reading it back out is unaffected.
+    union Buffer<R> {
+        value: mem::ManuallyDrop<R>,
+        _register: raw::ffi_arg,
+    }
+
+    let mut result = mem::MaybeUninit::<Buffer<R>>::uninit();
+    raw::ffi_call(
+        cif,
+        Some(*fun.as_fun()),
+        result.as_mut_ptr() as *mut c_void,
+        args,
+    );
+    mem::ManuallyDrop::into_inner(result.assume_init().value)
+}
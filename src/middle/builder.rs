@@ -0,0 +1,54 @@
+//! A small ergonomic helper for assembling a [`Cif`](../struct.Cif.html)
+//! without writing out a `Vec` of argument types by hand.
+
+use super::{Cif, Type};
+
+/// Accumulates argument types and a result type, then builds a
+/// [`Cif`](../struct.Cif.html).
+///
+/// ```
+/// use libffi::middle::{Builder, Type};
+///
+/// let cif = Builder::new()
+///     .arg(Type::i32())
+///     .arg(Type::i32())
+///     .res(Type::i32())
+///     .into_cif();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    args: Vec<Type>,
+    res: Option<Type>,
+}
+
+impl Builder {
+    /// Creates a new, empty `Builder`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an argument type.
+    pub fn arg(mut self, type_: Type) -> Self {
+        self.args.push(type_);
+        self
+    }
+
+    /// Adds several argument types at once.
+    pub fn args<I: IntoIterator<Item = Type>>(mut self, types: I) -> Self {
+        self.args.extend(types);
+        self
+    }
+
+    /// Sets the result type. If never called, the result type defaults
+    /// to `void`.
+    pub fn res(mut self, type_: Type) -> Self {
+        self.res = Some(type_);
+        self
+    }
+
+    /// Builds the [`Cif`](../struct.Cif.html) from the accumulated
+    /// argument and result types.
+    pub fn into_cif(self) -> Cif {
+        Cif::new(self.args, self.res.unwrap_or_else(Type::void))
+    }
+}
@@ -0,0 +1,75 @@
+//! Memory-managed C closures, i.e., C function pointers that carry
+//! their own associated data.
+
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+use crate::low;
+use crate::raw;
+
+use super::Cif;
+
+/// Represents a C closure, keeping the underlying `ffi_closure`
+/// allocation alive for as long as the `Closure` is alive, and freeing
+/// it on drop.
+///
+/// This type is the foundation the [`high`](../../high/index.html)
+/// layer’s `ClosureN` wrappers build on; it doesn’t know anything about
+/// the type of the callback it wraps, beyond its raw code pointer.
+pub struct Closure<'a> {
+    alloc: *mut raw::ffi_closure,
+    code: low::CodePtr,
+    _cif: &'a Cif,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for Closure<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            raw::ffi_closure_free(self.alloc as *mut c_void);
+        }
+    }
+}
+
+impl<'a> Closure<'a> {
+    /// Creates a new closure from the given CIF, a trampoline function
+    /// implementing the callback ABI, and a pointer to user data that
+    /// will be handed back to the trampoline on every call.
+    ///
+    /// # Safety
+    ///
+    /// `callback` must have a signature compatible with `cif`, and
+    /// `userdata` must outlive the returned `Closure`.
+    pub unsafe fn new(
+        cif: &'a Cif,
+        callback: raw::ffi_closure_fun,
+        userdata: *const c_void,
+    ) -> Self {
+        let mut code_ptr: *mut c_void = std::ptr::null_mut();
+        let alloc = raw::ffi_closure_alloc(
+            std::mem::size_of::<raw::ffi_closure>(),
+            &mut code_ptr as *mut *mut c_void,
+        ) as *mut raw::ffi_closure;
+
+        let status = raw::ffi_prep_closure_loc(
+            alloc,
+            &cif.cif as *const low::ffi_cif as *mut low::ffi_cif,
+            callback,
+            userdata as *mut c_void,
+            code_ptr,
+        );
+        assert_eq!(status, raw::ffi_status_FFI_OK);
+
+        Closure {
+            alloc,
+            code: low::CodePtr::from_ptr(code_ptr as *const c_void),
+            _cif: cif,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gets the C code pointer that can be handed to C as a callback.
+    pub fn code_ptr(&self) -> &low::CodePtr {
+        &self.code
+    }
+}
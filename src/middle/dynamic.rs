@@ -0,0 +1,325 @@
+//! Type-erased, runtime-typed calls.
+//!
+//! Building a monomorphized Rust call site for every foreign function
+//! signature doesn't work for an interpreter, where signatures are
+//! only known once a script runs. This module trades that away for a
+//! single dynamic entry point: a runtime-constructed
+//! [`Signature`](struct.Signature.html) plus a slice of type-erased
+//! [`Value`](enum.Value.html)s drives the CIF construction, argument
+//! marshalling, and the call itself.
+
+use std::os::raw::c_void;
+
+use crate::low::CodePtr;
+
+use super::{Arg, Cif, Type};
+
+/// A runtime tag for one of the scalar types libffi understands.
+///
+/// Mirrors [`Type`](struct.Type.html)’s scalar constructors; unlike
+/// `Type`, a `Tag` is `Copy` and cheap to carry around in a
+/// runtime-built [`Signature`](struct.Signature.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tag {
+    /// No value — only meaningful as a result type.
+    Void,
+    /// `u8`
+    U8,
+    /// `i8`
+    I8,
+    /// `u16`
+    U16,
+    /// `i16`
+    I16,
+    /// `u32`
+    U32,
+    /// `i32`
+    I32,
+    /// `u64`
+    U64,
+    /// `i64`
+    I64,
+    /// `f32`
+    F32,
+    /// `f64`
+    F64,
+    /// Any pointer.
+    Pointer,
+}
+
+impl Tag {
+    fn to_type(self) -> Type {
+        match self {
+            Tag::Void => Type::void(),
+            Tag::U8 => Type::u8(),
+            Tag::I8 => Type::i8(),
+            Tag::U16 => Type::u16(),
+            Tag::I16 => Type::i16(),
+            Tag::U32 => Type::u32(),
+            Tag::I32 => Type::i32(),
+            Tag::U64 => Type::u64(),
+            Tag::I64 => Type::i64(),
+            Tag::F32 => Type::f32(),
+            Tag::F64 => Type::f64(),
+            Tag::Pointer => Type::pointer(),
+        }
+    }
+}
+
+/// A type-erased scalar value, for passing as an argument to, or
+/// receiving as the result of, a dynamic [`call`](fn.call.html).
+#[derive(Clone, Copy, Debug)]
+pub enum Value {
+    /// No value.
+    Void,
+    /// `u8`
+    U8(u8),
+    /// `i8`
+    I8(i8),
+    /// `u16`
+    U16(u16),
+    /// `i16`
+    I16(i16),
+    /// `u32`
+    U32(u32),
+    /// `i32`
+    I32(i32),
+    /// `u64`
+    U64(u64),
+    /// `i64`
+    I64(i64),
+    /// `f32`
+    F32(f32),
+    /// `f64`
+    F64(f64),
+    /// Any pointer.
+    Pointer(*mut c_void),
+}
+
+impl Value {
+    /// The [`Tag`](enum.Tag.html) describing this value’s type.
+    pub fn tag(&self) -> Tag {
+        match *self {
+            Value::Void => Tag::Void,
+            Value::U8(_) => Tag::U8,
+            Value::I8(_) => Tag::I8,
+            Value::U16(_) => Tag::U16,
+            Value::I16(_) => Tag::I16,
+            Value::U32(_) => Tag::U32,
+            Value::I32(_) => Tag::I32,
+            Value::U64(_) => Tag::U64,
+            Value::I64(_) => Tag::I64,
+            Value::F32(_) => Tag::F32,
+            Value::F64(_) => Tag::F64,
+            Value::Pointer(_) => Tag::Pointer,
+        }
+    }
+
+    fn as_arg(&self) -> Arg {
+        match *self {
+            Value::Void => Arg::new(&()),
+            Value::U8(ref v) => Arg::new(v),
+            Value::I8(ref v) => Arg::new(v),
+            Value::U16(ref v) => Arg::new(v),
+            Value::I16(ref v) => Arg::new(v),
+            Value::U32(ref v) => Arg::new(v),
+            Value::I32(ref v) => Arg::new(v),
+            Value::U64(ref v) => Arg::new(v),
+            Value::I64(ref v) => Arg::new(v),
+            Value::F32(ref v) => Arg::new(v),
+            Value::F64(ref v) => Arg::new(v),
+            Value::Pointer(ref v) => Arg::new(v),
+        }
+    }
+}
+
+/// A runtime-constructed function signature: the tags of its arguments,
+/// in order, plus its result tag.
+#[derive(Clone, Debug)]
+pub struct Signature {
+    args: Vec<Tag>,
+    result: Tag,
+}
+
+impl Signature {
+    /// Creates a new signature from argument tags and a result tag.
+    pub fn new<I: IntoIterator<Item = Tag>>(args: I, result: Tag) -> Self {
+        Signature {
+            args: args.into_iter().collect(),
+            result,
+        }
+    }
+}
+
+/// The arguments supplied to [`call`](fn.call.html) didn’t match the
+/// [`Signature`](struct.Signature.html)’s declared argument types.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CallError {
+    /// `args` didn’t have as many elements as `signature` declared.
+    Arity {
+        /// Number of arguments `signature` declared.
+        expected: usize,
+        /// Number of arguments actually supplied.
+        found: usize,
+    },
+    /// The argument at `index` didn’t have the tag `signature` declared
+    /// for it, or `signature` declared `Tag::Void` for it — `Void` is
+    /// only meaningful as a result type, never as an argument type.
+    TypeMismatch {
+        /// Index of the mismatched argument.
+        index: usize,
+        /// The tag `signature` declared for this argument.
+        expected: Tag,
+        /// The tag of the value actually supplied.
+        found: Tag,
+    },
+}
+
+/// Calls `fun` according to a runtime-constructed `signature`, checking
+/// that `args` matches it tag-for-tag before dispatching, and returning
+/// a type-erased result.
+///
+/// This is the single dynamic entry point an interpreter can use
+/// instead of generating a monomorphized Rust call site per foreign
+/// signature: the CIF, argument marshalling, and the call itself are
+/// all driven by `signature` and `args` alone.
+///
+/// # Safety
+///
+/// `fun` must actually be callable according to `signature`, using the
+/// platform’s default C calling convention. Tag-checking `args` rules
+/// out mismatched *Rust* representations, but libffi itself is not
+/// consulted about whether `signature` matches what `fun` expects.
+pub unsafe fn call(
+    fun: CodePtr,
+    signature: &Signature,
+    args: &[Value],
+) -> Result<Value, CallError> {
+    if args.len() != signature.args.len() {
+        return Err(CallError::Arity {
+            expected: signature.args.len(),
+            found: args.len(),
+        });
+    }
+    for (index, (&expected, value)) in signature.args.iter().zip(args).enumerate() {
+        let found = value.tag();
+        if expected == Tag::Void || found != expected {
+            return Err(CallError::TypeMismatch {
+                index,
+                expected,
+                found,
+            });
+        }
+    }
+
+    let cif = Cif::new(
+        signature.args.iter().map(|t| t.to_type()),
+        signature.result.to_type(),
+    );
+    let raw_args: Vec<Arg> = args.iter().map(Value::as_arg).collect();
+
+    let result = match signature.result {
+        Tag::Void => {
+            cif.call::<()>(fun, &raw_args);
+            Value::Void
+        }
+        Tag::U8 => Value::U8(cif.call(fun, &raw_args)),
+        Tag::I8 => Value::I8(cif.call(fun, &raw_args)),
+        Tag::U16 => Value::U16(cif.call(fun, &raw_args)),
+        Tag::I16 => Value::I16(cif.call(fun, &raw_args)),
+        Tag::U32 => Value::U32(cif.call(fun, &raw_args)),
+        Tag::I32 => Value::I32(cif.call(fun, &raw_args)),
+        Tag::U64 => Value::U64(cif.call(fun, &raw_args)),
+        Tag::I64 => Value::I64(cif.call(fun, &raw_args)),
+        Tag::F32 => Value::F32(cif.call(fun, &raw_args)),
+        Tag::F64 => Value::F64(cif.call(fun, &raw_args)),
+        Tag::Pointer => Value::Pointer(cif.call(fun, &raw_args)),
+    };
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[test]
+    fn arity_mismatch_is_reported() {
+        let signature = Signature::new(vec![Tag::I32, Tag::I32], Tag::I32);
+        let err = unsafe {
+            call(
+                CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &signature,
+                &[Value::I32(1)],
+            )
+        }
+        .unwrap_err();
+        assert_eq!(
+            CallError::Arity {
+                expected: 2,
+                found: 1,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn argument_type_mismatch_is_reported() {
+        let signature = Signature::new(vec![Tag::I32, Tag::I32], Tag::I32);
+        let err = unsafe {
+            call(
+                CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &signature,
+                &[Value::I32(1), Value::U8(2)],
+            )
+        }
+        .unwrap_err();
+        assert_eq!(
+            CallError::TypeMismatch {
+                index: 1,
+                expected: Tag::I32,
+                found: Tag::U8,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn void_is_rejected_as_an_argument_type() {
+        let signature = Signature::new(vec![Tag::Void], Tag::I32);
+        let err = unsafe {
+            call(
+                CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &signature,
+                &[Value::Void],
+            )
+        }
+        .unwrap_err();
+        assert_eq!(
+            CallError::TypeMismatch {
+                index: 0,
+                expected: Tag::Void,
+                found: Tag::Void,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn dispatches_a_well_typed_call() {
+        let signature = Signature::new(vec![Tag::I32, Tag::I32], Tag::I32);
+        let result = unsafe {
+            call(
+                CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &signature,
+                &[Value::I32(1), Value::I32(2)],
+            )
+        }
+        .unwrap();
+        assert_eq!(Value::I32(3).tag(), result.tag());
+    }
+}
@@ -0,0 +1,232 @@
+//! Middle layer providing a somewhat safer (but still quite unsafe)
+//! API than the [`low`](../low/index.html) layer.
+//!
+//! This layer takes care of memory management for the C
+//! data structures, and provides a higher-level API for assembling CIFs
+//! and closures, but doesn’t check types.
+
+use std::os::raw::c_void;
+
+mod types;
+pub use self::types::Type;
+
+mod builder;
+pub use self::builder::Builder;
+
+mod closure;
+pub use self::closure::Closure;
+
+pub mod dynamic;
+
+use crate::low;
+
+/// Represents a single argument when calling a function via a
+/// [`Cif`](struct.Cif.html).
+///
+/// In particular, `Arg` wraps a pointer to the data, as it needs to be
+/// passed to [`ffi_call`](../raw/fn.ffi_call.html).
+///
+/// `#[repr(transparent)]` because [`Cif::call`](struct.Cif.html#method.call)
+/// reinterprets a `&[Arg]` buffer as `*mut *mut c_void` by pointer cast;
+/// this pins `Arg`'s layout to a bare pointer so that cast stays sound.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct Arg(*mut c_void);
+
+impl Arg {
+    /// Constructs an `Arg` from a reference to any `C`-compatible type.
+    pub fn new<T>(r: &T) -> Self {
+        Arg(r as *const T as *mut c_void)
+    }
+}
+
+/// Coerces a reference to a `C`-compatible type into an
+/// [`Arg`](struct.Arg.html).
+pub fn arg<T>(r: &T) -> Arg {
+    Arg::new(r)
+}
+
+/// Represents a prepared call interface, ready to invoke a foreign
+/// function.
+///
+/// This is the core type of the `middle` layer; it owns the argument
+/// and return types that the C library needs to keep alive for the
+/// lifetime of the CIF.
+#[derive(Debug)]
+pub struct Cif {
+    cif: low::ffi_cif,
+    args: Vec<Type>,
+    result: Type,
+    /// Number of leading fixed arguments, if this CIF was prepared with
+    /// [`new_variadic`](#method.new_variadic.html).
+    nfixedargs: Option<usize>,
+    // `ffi_prep_cif`/`ffi_prep_cif_var` only copy this *pointer* into
+    // `cif`, not the `ffi_type`s it points at -- libffi dereferences it
+    // again on every call. Has to live exactly as long as `cif` does.
+    arg_type_ptrs: Vec<*mut low::ffi_type>,
+}
+
+unsafe impl Send for Cif {}
+
+impl Cif {
+    /// Creates a new [`Cif`](struct.Cif.html) for the given argument and
+    /// result types, using the default calling convention.
+    pub fn new<I>(args: I, result: Type) -> Self
+    where
+        I: IntoIterator<Item = Type>,
+    {
+        let args: Vec<Type> = args.into_iter().collect();
+        let mut cif = Cif {
+            cif: Default::default(),
+            args,
+            result,
+            nfixedargs: None,
+            arg_type_ptrs: Vec::new(),
+        };
+        cif.prep();
+        cif
+    }
+
+    /// Creates a new variadic [`Cif`](struct.Cif.html), where
+    /// `fixed_args` is the list of always-present leading arguments,
+    /// and `var_args` is the concrete, call-specific list of types for
+    /// the trailing variadic arguments.
+    ///
+    /// Each distinct combination of variadic argument types needs its
+    /// own `Cif` — the CIF is only valid for calls whose variadic
+    /// arguments match `var_args` exactly, mirroring the requirement
+    /// the underlying `ffi_prep_cif_var` places on callers.
+    pub fn new_variadic<I, J>(fixed_args: I, var_args: J, result: Type) -> Self
+    where
+        I: IntoIterator<Item = Type>,
+        J: IntoIterator<Item = Type>,
+    {
+        let mut args: Vec<Type> = fixed_args.into_iter().collect();
+        let nfixedargs = args.len();
+        args.extend(var_args);
+
+        let mut cif = Cif {
+            cif: Default::default(),
+            args,
+            result,
+            nfixedargs: Some(nfixedargs),
+            arg_type_ptrs: Vec::new(),
+        };
+        cif.prep();
+        cif
+    }
+
+    /// True if this CIF was built with
+    /// [`new_variadic`](#method.new_variadic.html).
+    pub fn is_variadic(&self) -> bool {
+        self.nfixedargs.is_some()
+    }
+
+    fn prep(&mut self) {
+        self.arg_type_ptrs = self.args.iter().map(|t| t.as_raw_ptr()).collect();
+
+        let result = unsafe {
+            match self.nfixedargs {
+                None => low::prep_cif(
+                    &mut self.cif,
+                    low::FFI_DEFAULT_ABI,
+                    self.arg_type_ptrs.len(),
+                    self.result.as_raw_ptr(),
+                    self.arg_type_ptrs.as_mut_ptr(),
+                ),
+                Some(nfixedargs) => low::prep_cif_var(
+                    &mut self.cif,
+                    low::FFI_DEFAULT_ABI,
+                    nfixedargs,
+                    self.arg_type_ptrs.len(),
+                    self.result.as_raw_ptr(),
+                    self.arg_type_ptrs.as_mut_ptr(),
+                ),
+            }
+        };
+
+        result.expect("ffi_prep_cif or ffi_prep_cif_var");
+    }
+
+    /// Calls the function `fun` with the given arguments, which must
+    /// match the types this CIF was built with, and the return type
+    /// `R`, which must match the CIF’s `result` type.
+    ///
+    /// # Safety
+    ///
+    /// The `fun` and `args` must actually conform to the CIF’s
+    /// signature, or undefined behavior results.
+    pub unsafe fn call<R>(&self, fun: low::CodePtr, args: &[Arg]) -> R {
+        assert_eq!(self.args.len(), args.len());
+
+        low::call::<R>(
+            &self.cif as *const low::ffi_cif as *mut low::ffi_cif,
+            fun,
+            args.as_ptr() as *mut *mut c_void,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    unsafe extern "C" fn not(a: u8) -> u8 {
+        if a == 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    #[test]
+    fn call_with_i32_args_and_result() {
+        let cif = Cif::new(vec![Type::i32(), Type::i32()], Type::i32());
+        let result: i32 = unsafe {
+            cif.call(
+                low::CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &[arg(&1i32), arg(&2i32)],
+            )
+        };
+        assert_eq!(3, result);
+    }
+
+    #[test]
+    fn variadic_cif_reports_is_variadic() {
+        let cif = Cif::new_variadic(vec![Type::i32()], vec![Type::i32()], Type::i32());
+        assert!(cif.is_variadic());
+
+        let fixed = Cif::new(vec![Type::i32()], Type::i32());
+        assert!(!fixed.is_variadic());
+    }
+
+    #[test]
+    fn call_variadic_cif() {
+        let cif = Cif::new_variadic(vec![Type::i32()], vec![Type::i32()], Type::i32());
+        let result: i32 = unsafe {
+            cif.call(
+                low::CodePtr::from_fun(std::mem::transmute(add as unsafe extern "C" fn(i32, i32) -> i32)),
+                &[arg(&1i32), arg(&2i32)],
+            )
+        };
+        assert_eq!(3, result);
+    }
+
+    #[test]
+    fn call_with_sub_register_result() {
+        // `u8` is narrower than an `ffi_arg`; exercises the
+        // register-widened return-value path in `low::call`.
+        let cif = Cif::new(vec![Type::u8()], Type::u8());
+        let result: u8 = unsafe {
+            cif.call(
+                low::CodePtr::from_fun(std::mem::transmute(not as unsafe extern "C" fn(u8) -> u8)),
+                &[arg(&0u8)],
+            )
+        };
+        assert_eq!(1, result);
+    }
+}